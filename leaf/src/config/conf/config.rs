@@ -1,8 +1,12 @@
 use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::{self, BufRead};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
 use anyhow::Result;
@@ -11,16 +15,19 @@ use regex::Regex;
 
 use crate::config::{external_rule, geosite, internal};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct TUN {
     pub name: Option<String>,
     pub address: Option<String>,
     pub netmask: Option<String>,
     pub gateway: Option<String>,
     pub mtu: Option<i32>,
+    // set when `mtu = auto` was given instead of a fixed number, triggers
+    // path-MTU probing at config-build time instead of the 1500 default
+    pub mtu_auto: bool,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct General {
     pub tun: Option<TUN>,
     pub tun_fd: Option<i32>,
@@ -32,9 +39,11 @@ pub struct General {
     pub port: Option<u16>,
     pub socks_interface: Option<String>,
     pub socks_port: Option<u16>,
+    pub systemd_notify: bool,
+    pub dns_cache_size: Option<u32>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Proxy {
     pub tag: String,
     pub protocol: String,
@@ -47,6 +56,10 @@ pub struct Proxy {
     // shadowsocks
     pub encrypt_method: Option<String>,
 
+    // shadowsocks, SIP003 plugin (e.g. "obfs-local", "v2ray-plugin")
+    pub plugin: Option<String>,
+    pub plugin_opts: Option<String>,
+
     // shadowsocks, trojan
     pub password: Option<String>,
 
@@ -55,6 +68,10 @@ pub struct Proxy {
     pub ws: Option<bool>,
     pub tls: Option<bool>,
     pub ws_path: Option<String>,
+    // Host fronting: a Host header distinct from the TCP `address`, plus any
+    // other extra WS upgrade request headers
+    pub ws_host: Option<String>,
+    pub ws_headers: Option<HashMap<String, String>>,
 
     // trojan
     pub sni: Option<String>,
@@ -69,16 +86,20 @@ impl Default for Proxy {
             address: None,
             port: None,
             encrypt_method: Some("chacha20-ietf-poly1305".to_string()),
+            plugin: None,
+            plugin_opts: None,
             password: None,
             username: None,
             ws: Some(false),
             tls: Some(false),
             ws_path: None,
+            ws_host: None,
+            ws_headers: None,
             sni: None,
         }
     }
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProxyGroup {
     pub tag: String,
     pub protocol: String,
@@ -109,14 +130,14 @@ impl Default for ProxyGroup {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Rule {
     pub type_field: String,
     pub filter: Option<String>,
     pub target: String,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Config {
     pub general: Option<General>,
     pub proxy: Option<Vec<Proxy>>,
@@ -230,7 +251,11 @@ pub fn from_lines(lines: Vec<io::Result<String>>) -> Result<Config> {
                     tun.address = Some(items[1].clone());
                     tun.netmask = Some(items[2].clone());
                     tun.gateway = Some(items[3].clone());
-                    tun.mtu = get_value::<i32>(&items[4]);
+                    if items[4].trim() == "auto" {
+                        tun.mtu_auto = true;
+                    } else {
+                        tun.mtu = get_value::<i32>(&items[4]);
+                    }
                     general.tun = Some(tun);
                 }
             }
@@ -258,6 +283,12 @@ pub fn from_lines(lines: Vec<io::Result<String>>) -> Result<Config> {
             "socks-port" => {
                 general.socks_port = get_value::<u16>(parts[1]);
             }
+            "systemd-notify" => {
+                general.systemd_notify = parts[1].trim() == "true";
+            }
+            "dns-cache-size" => {
+                general.dns_cache_size = get_value::<u32>(parts[1]);
+            }
             _ => {}
         }
     }
@@ -290,7 +321,9 @@ pub fn from_lines(lines: Vec<io::Result<String>>) -> Result<Config> {
         // extract key-value params
         // let params = &params[2..];
         for param in &params {
-            let parts: Vec<&str> = param.split('=').collect();
+            // splitn(2, ..) so a value like plugin-opts can itself contain
+            // "=" (e.g. "obfs=tls;obfs-host=example.com")
+            let parts: Vec<&str> = param.splitn(2, '=').collect();
             if parts.len() != 2 {
                 continue;
             }
@@ -306,6 +339,12 @@ pub fn from_lines(lines: Vec<io::Result<String>>) -> Result<Config> {
                 "password" => {
                     proxy.password = Some(v.to_string());
                 }
+                "plugin" => {
+                    proxy.plugin = Some(v.to_string());
+                }
+                "plugin-opts" => {
+                    proxy.plugin_opts = Some(v.to_string());
+                }
                 "username" => {
                     proxy.username = Some(v.to_string());
                 }
@@ -314,6 +353,22 @@ pub fn from_lines(lines: Vec<io::Result<String>>) -> Result<Config> {
                 "ws-path" => {
                     proxy.ws_path = Some(v.to_string());
                 }
+                "ws-host" => {
+                    proxy.ws_host = Some(v.to_string());
+                }
+                "ws-headers" => {
+                    // "Key1:Value1|Key2:Value2", '|' avoids clashing with
+                    // the outer ',' param separator
+                    let mut headers = HashMap::new();
+                    for header in v.split('|') {
+                        if let Some((name, value)) = header.split_once(':') {
+                            headers.insert(name.trim().to_string(), value.trim().to_string());
+                        }
+                    }
+                    if !headers.is_empty() {
+                        proxy.ws_headers = Some(headers);
+                    }
+                }
                 "sni" => {
                     proxy.sni = Some(v.to_string());
                 }
@@ -507,7 +562,8 @@ pub fn from_lines(lines: Vec<io::Result<String>>) -> Result<Config> {
         rule.target = params[2].to_string();
 
         match rule.type_field.as_str() {
-            "IP-CIDR" | "DOMAIN" | "DOMAIN-SUFFIX" | "DOMAIN-KEYWORD" | "GEOIP" | "EXTERNAL" => {
+            "IP-CIDR" | "DOMAIN" | "DOMAIN-SUFFIX" | "DOMAIN-KEYWORD" | "DOMAIN-WILDCARD" | "GEOIP"
+            | "EXTERNAL" => {
                 rule.filter = Some(params[1].to_string());
             }
             _ => {}
@@ -525,7 +581,535 @@ pub fn from_lines(lines: Vec<io::Result<String>>) -> Result<Config> {
     Ok(config)
 }
 
+/// A compiled `DOMAIN-WILDCARD` pattern. Patterns with no glob metacharacters
+/// (`*`, `?`, `[...]`) are kept as a plain string so matching can take the
+/// cheap `==` path instead of always paying for glob evaluation. The by far
+/// most common wildcard shape, a leading `*.` with no other metacharacters
+/// (e.g. `*.example.com`), is recognized as `Suffix` because it is exactly
+/// what `DOMAIN-SUFFIX` already matches — `internal::RoutingRule_Domain_Type`
+/// has no `WILDCARD` variant and the router has no glob matcher, so reusing
+/// the existing `DOMAIN` (suffix) type is how `to_internal` gives it real
+/// matching instead of a type the router doesn't understand. Anything else
+/// with glob metacharacters is rejected at load time (see `to_internal`'s
+/// `DOMAIN-WILDCARD` arm) with a clear error instead of silently matching
+/// nothing.
+#[derive(Debug, Clone)]
+pub enum WildcardDomain {
+    Literal(String),
+    Suffix(String),
+    Glob(glob::Pattern),
+}
+
+impl WildcardDomain {
+    pub fn compile(pattern: &str) -> Result<Self> {
+        if let Some(suffix) = pattern.strip_prefix("*.") {
+            if !suffix.contains(['*', '?', '[']) && !suffix.is_empty() {
+                return Ok(WildcardDomain::Suffix(suffix.to_string()));
+            }
+        }
+        if pattern.contains(['*', '?', '[']) {
+            let compiled = glob::Pattern::new(pattern)
+                .map_err(|e| anyhow!("invalid DOMAIN-WILDCARD pattern {:?}: {}", pattern, e))?;
+            Ok(WildcardDomain::Glob(compiled))
+        } else {
+            Ok(WildcardDomain::Literal(pattern.to_string()))
+        }
+    }
+
+    pub fn matches(&self, domain: &str) -> bool {
+        match self {
+            WildcardDomain::Literal(s) => s == domain,
+            WildcardDomain::Suffix(s) => domain == s || domain.ends_with(&format!(".{}", s)),
+            WildcardDomain::Glob(p) => p.matches(domain),
+        }
+    }
+}
+
+// cache of gateway -> discovered path MTU, so a reload or a second tun
+// section pointing at the same gateway doesn't re-probe
+fn mtu_cache() -> &'static std::sync::Mutex<HashMap<String, i32>> {
+    static CACHE: std::sync::OnceLock<std::sync::Mutex<HashMap<String, i32>>> = std::sync::OnceLock::new();
+    CACHE.get_or_init(|| std::sync::Mutex::new(HashMap::new()))
+}
+
+const MTU_PROBE_HIGH: i32 = 1500;
+const MTU_PROBE_LOW: i32 = 576;
+// IPv4 + UDP header overhead: `best` is the largest UDP *payload* that made
+// it through unfragmented, so the link MTU is that payload size plus this
+const MTU_PROBE_OVERHEAD: i32 = 28;
+
+// Reads the socket's error queue for an ICMP "fragmentation needed"
+// (type 3, code 4) report queued by `IP_RECVERR`, returning the next-hop
+// MTU the router reported in `sock_extended_err.ee_info`. Must be called
+// right after a `send` that might have provoked one.
+#[cfg(target_os = "linux")]
+fn read_frag_needed_mtu(fd: libc::c_int) -> Option<i32> {
+    unsafe {
+        let mut cmsg_buf = [0u8; 256];
+        let mut iov = libc::iovec { iov_base: std::ptr::null_mut(), iov_len: 0 };
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = cmsg_buf.len() as _;
+
+        if libc::recvmsg(fd, &mut msg, libc::MSG_ERRQUEUE) < 0 {
+            return None;
+        }
+
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let c = &*cmsg;
+            if c.cmsg_level == libc::IPPROTO_IP && c.cmsg_type == libc::IP_RECVERR {
+                let err = &*(libc::CMSG_DATA(cmsg) as *const libc::sock_extended_err);
+                if err.ee_origin == libc::SO_EE_ORIGIN_ICMP && err.ee_type == 3 && err.ee_code == 4 {
+                    return Some(err.ee_info as i32);
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+        None
+    }
+}
+
+// `IP_PMTUDISC_DO` short-circuits against the kernel's cached route MTU
+// without ever putting a packet on the wire, so it only ever measured the
+// local interface's MTU, never the real path MTU through `gateway`.
+// `IP_PMTUDISC_PROBE` disables that shortcut (every send is forced out
+// unfragmented, ignoring the route cache) and, combined with `IP_RECVERR`,
+// queues the ICMP "fragmentation needed" response from whichever hop
+// actually rejected an oversized probe, which is read back via
+// `read_frag_needed_mtu`.
+#[cfg(target_os = "linux")]
+fn probe_path_mtu(gateway: &str) -> Option<i32> {
+    use std::net::UdpSocket;
+    use std::os::unix::io::AsRawFd;
+    use std::time::Duration;
+
+    let sock = UdpSocket::bind("0.0.0.0:0").ok()?;
+    sock.connect((gateway, 33434)).ok()?;
+    sock.set_read_timeout(Some(Duration::from_millis(300))).ok()?;
+
+    let fd = sock.as_raw_fd();
+    unsafe {
+        let val: libc::c_int = libc::IP_PMTUDISC_PROBE;
+        libc::setsockopt(
+            fd,
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            &val as *const _ as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        );
+        let enable: libc::c_int = 1;
+        libc::setsockopt(
+            fd,
+            libc::IPPROTO_IP,
+            libc::IP_RECVERR,
+            &enable as *const _ as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        );
+    }
+
+    // binary search the largest payload that doesn't provoke a "fragmentation
+    // needed" ICMP error from somewhere along the path; a probe that does get
+    // one reported hands back the router's own MTU figure directly, which is
+    // authoritative and ends the search immediately
+    let mut low = MTU_PROBE_LOW;
+    let mut high = MTU_PROBE_HIGH;
+    let mut best = MTU_PROBE_LOW;
+    let mut confirmed = false;
+    while low <= high {
+        let mid = low + (high - low) / 2;
+        let payload = vec![0u8; mid as usize];
+        if let Err(e) = sock.send(&payload) {
+            // only EMSGSIZE means "too big for this path"; anything else
+            // (ENETUNREACH, EHOSTUNREACH, ...) is unrelated to size and
+            // would bias the search if treated the same way, so stop here
+            // and report what's been confirmed to fit so far instead of
+            // guessing further; with nothing confirmed yet, probing has
+            // failed and the caller's fallback applies
+            if e.raw_os_error() == Some(libc::EMSGSIZE) {
+                high = mid - 1;
+                continue;
+            }
+            if !confirmed {
+                return None;
+            }
+            break;
+        }
+        match read_frag_needed_mtu(fd) {
+            Some(reported_mtu) => return Some(reported_mtu),
+            None => {
+                confirmed = true;
+                best = mid;
+                low = mid + 1;
+            }
+        }
+    }
+    Some(best + MTU_PROBE_OVERHEAD)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn probe_path_mtu(_gateway: &str) -> Option<i32> {
+    None
+}
+
+// runs path-MTU discovery once at config-build time (never on the
+// per-packet path) and falls back to 1500 if probing fails or the gateway
+// is unknown
+fn resolve_auto_mtu(gateway: Option<&String>) -> i32 {
+    let gateway = match gateway {
+        Some(g) => g.clone(),
+        None => return MTU_PROBE_HIGH,
+    };
+    if let Some(mtu) = mtu_cache().lock().unwrap().get(&gateway) {
+        return *mtu;
+    }
+    let mtu = probe_path_mtu(&gateway).unwrap_or(MTU_PROBE_HIGH);
+    mtu_cache().lock().unwrap().insert(gateway, mtu);
+    mtu
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn warn(message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warn,
+            message: message.into(),
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+}
+
+// picks the on-disk path, if any, out of an EXTERNAL rule filter; `site:`
+// names a geosite tag (no file of its own), `mmdb:` is followed by a path
+// and then optional `:`-separated fields, and anything else is a bare path
+fn external_rule_file_path(filter: &str) -> Option<&str> {
+    if filter.starts_with("site:") {
+        None
+    } else if let Some(rest) = filter.strip_prefix("mmdb:") {
+        rest.split(':').next()
+    } else {
+        Some(filter)
+    }
+}
+
+/// Surfaces mistakes `from_lines` currently swallows instead of letting them
+/// silently produce a dead proxy group or a config with no catch-all route.
+/// Errors should abort [`to_internal`]; warnings are for logging only.
+pub fn validate(conf: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let mut seen_tags = HashMap::new();
+    let mut known_tags = std::collections::HashSet::new();
+    for proxy in conf.proxy.iter().flatten() {
+        known_tags.insert(proxy.tag.clone());
+        if let Some(prev_line) = seen_tags.insert(proxy.tag.clone(), ()) {
+            let _ = prev_line;
+            diagnostics.push(Diagnostic::error(format!("duplicate proxy tag \"{}\"", proxy.tag)));
+        }
+    }
+    for group in conf.proxy_group.iter().flatten() {
+        known_tags.insert(group.tag.clone());
+        if seen_tags.insert(group.tag.clone(), ()).is_some() {
+            diagnostics.push(Diagnostic::error(format!(
+                "duplicate proxy group tag \"{}\"",
+                group.tag
+            )));
+        }
+    }
+
+    for group in conf.proxy_group.iter().flatten() {
+        for actor in group.actors.iter().flatten() {
+            if !known_tags.contains(actor) {
+                diagnostics.push(Diagnostic::error(format!(
+                    "proxy group \"{}\" references unknown actor \"{}\"",
+                    group.tag, actor
+                )));
+            }
+        }
+        if group.protocol == "failover" {
+            let check_interval = group.check_interval.unwrap_or(300);
+            let fail_timeout = group.fail_timeout.unwrap_or(4);
+            if check_interval < fail_timeout {
+                diagnostics.push(Diagnostic::warn(format!(
+                    "proxy group \"{}\" has check-interval ({}) smaller than fail-timeout ({})",
+                    group.tag, check_interval, fail_timeout
+                )));
+            }
+        }
+    }
+
+    let mut has_final = false;
+    for rule in conf.rule.iter().flatten() {
+        if rule.type_field == "FINAL" {
+            has_final = true;
+        }
+        if rule.type_field == "EXTERNAL" {
+            if let Some(filter) = &rule.filter {
+                // only `mmdb:<path>[:...]` forms and bare paths name an
+                // actual file on disk; `site:` references a tag inside
+                // already-loaded geosite data, not a path
+                if let Some(path) = external_rule_file_path(filter) {
+                    if std::fs::metadata(path).is_err() {
+                        diagnostics.push(Diagnostic::warn(format!(
+                            "EXTERNAL rule file \"{}\" is missing or unreadable",
+                            path
+                        )));
+                    }
+                }
+            }
+        }
+    }
+    if !has_final {
+        diagnostics.push(Diagnostic::warn("no FINAL rule, unmatched traffic has no default route"));
+    }
+
+    diagnostics
+}
+
+// translates a SIP003 plugin + plugin-opts pair into the (tls, ws, host,
+// path) chain actors leaf already knows how to build, so common obfs modes
+// work without a dedicated plugin outbound
+fn translate_plugin(plugin: &str, opts: &str) -> (bool, bool, Option<String>, Option<String>) {
+    let mut opt_map = HashMap::new();
+    for kv in opts.split(';') {
+        let kv = kv.trim();
+        if kv.is_empty() {
+            continue;
+        }
+        match kv.split_once('=') {
+            Some((k, v)) => opt_map.insert(k.trim().to_string(), v.trim().to_string()),
+            None => opt_map.insert(kv.to_string(), "true".to_string()),
+        };
+    }
+    match plugin {
+        "obfs-local" | "simple-obfs" => {
+            let tls = opt_map.get("obfs").map(|v| v == "tls").unwrap_or(false);
+            let host = opt_map.get("obfs-host").cloned();
+            (tls, false, host, None)
+        }
+        "v2ray-plugin" => {
+            let ws = opt_map.get("mode").map(|v| v == "websocket").unwrap_or(true);
+            let tls = opt_map.contains_key("tls");
+            let host = opt_map.get("host").cloned();
+            let path = opt_map.get("path").cloned();
+            (tls, ws, host, path)
+        }
+        _ => (false, false, None, None),
+    }
+}
+
+// leaf's internal DNS message only has `servers: repeated string`, with no
+// sub-message to carry a transport and no DoH/DoT client in the resolver to
+// act on one, so there is no proto-level home for this yet. `DnsTransport`
+// exists anyway so the scheme prefix is parsed and validated once, here,
+// rather than every future reader of `dns.servers` having to re-derive the
+// address/port from the string; it still serializes back to a plain string,
+// unchanged except that a `tls://` entry without a port gets `:853` appended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DnsTransport {
+    Udp,
+    Tls,
+    Https,
+}
+
+fn parse_dns_server(spec: &str) -> (DnsTransport, String) {
+    if let Some(rest) = spec.strip_prefix("https://") {
+        (DnsTransport::Https, format!("https://{}", rest))
+    } else if let Some(rest) = spec.strip_prefix("tls://") {
+        let (address, port) = split_host_port(rest, 853);
+        (DnsTransport::Tls, format!("tls://{}:{}", address, port))
+    } else {
+        (DnsTransport::Udp, spec.trim().to_string())
+    }
+}
+
+fn normalize_dns_server(spec: &str) -> String {
+    parse_dns_server(spec).1
+}
+
+// builds the WS upgrade request headers for a proxy, layering an explicit
+// `ws-host` on top of `ws-headers` so a caller only needs to set one of
+// them for the common domain-fronting case
+fn build_ws_headers(ext_proxy: &Proxy) -> HashMap<String, String> {
+    let mut headers = ext_proxy.ws_headers.clone().unwrap_or_default();
+    if let Some(ws_host) = &ext_proxy.ws_host {
+        headers.insert("Host".to_string(), ws_host.clone());
+    }
+    headers
+}
+
+fn split_host_port(spec: &str, default_port: u16) -> (String, u32) {
+    // bracketed `[v6]:port`, the unambiguous form for an IPv6 literal with
+    // an explicit port
+    if let Some(rest) = spec.strip_prefix('[') {
+        if let Some(end) = rest.find(']') {
+            let host = &rest[..end];
+            let port = rest[end + 1..]
+                .strip_prefix(':')
+                .and_then(|p| p.parse::<u16>().ok())
+                .unwrap_or(default_port);
+            return (host.to_string(), port as u32);
+        }
+    }
+    // a bare address with more than one ':' is an unbracketed IPv6 literal,
+    // not a "host:port" pair (that syntax is ambiguous, so no port can be
+    // split off it)
+    if spec.matches(':').count() > 1 {
+        return (spec.to_string(), default_port as u32);
+    }
+    match spec.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => (host.to_string(), port.parse().unwrap()),
+        _ => (spec.to_string(), default_port as u32),
+    }
+}
+
+// how many entries `Reloader`'s DNS answer cache holds when `[General]` has
+// no `dns-cache-size` override
+const DEFAULT_DNS_CACHE_SIZE: u32 = 4096;
+const DNS_NEGATIVE_TTL: Duration = Duration::from_secs(30);
+const DNS_MAX_POSITIVE_TTL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone)]
+pub struct CachedAnswer {
+    pub records: Vec<Vec<u8>>,
+    // RRSIGs covering `records`, kept alongside them so a later DNSSEC
+    // validation step still has access rather than them being discarded
+    pub rrsigs: Vec<Vec<u8>>,
+    pub expires_at: Instant,
+    pub negative: bool,
+}
+
+/// Bounded LRU cache for DNS answers, keyed on `(name, record_type)`, used
+/// to avoid hammering upstream every time a routing decision (`GEOIP`,
+/// domain rules) needs a resolution. Entries carry an absolute expiry
+/// computed from the minimum RR TTL at insertion time; `get` treats expired
+/// entries as misses. Negative answers (NXDOMAIN/empty) are cached too with
+/// a short, bounded TTL so repeated upstream failures don't stampede it.
+pub struct DnsCache {
+    capacity: usize,
+    entries: HashMap<(String, u16), CachedAnswer>,
+    // recency order, kept in sync with `entries`: the front is the next
+    // eviction candidate, and every hit or re-insertion moves its key to
+    // the back so hot keys don't get evicted ahead of cold ones
+    order: std::collections::VecDeque<(String, u16)>,
+}
+
+impl DnsCache {
+    pub fn new(capacity: usize) -> Self {
+        DnsCache {
+            capacity,
+            entries: HashMap::new(),
+            order: std::collections::VecDeque::new(),
+        }
+    }
+
+    pub fn get(&mut self, name: &str, record_type: u16) -> Option<CachedAnswer> {
+        let key = (name.to_ascii_lowercase(), record_type);
+        match self.entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                let answer = entry.clone();
+                self.touch(&key);
+                Some(answer)
+            }
+            Some(_) => {
+                self.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    // moves `key` to the back of `order` (most-recently-used end)
+    fn touch(&mut self, key: &(String, u16)) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            self.order.remove(pos);
+        }
+        self.order.push_back(key.clone());
+    }
+
+    // drops `key` from both `entries` and `order`, keeping them in sync so
+    // `order` never holds a ghost key for an entry that is already gone
+    fn remove(&mut self, key: &(String, u16)) {
+        self.entries.remove(key);
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            self.order.remove(pos);
+        }
+    }
+
+    pub fn insert_positive(&mut self, name: &str, record_type: u16, records: Vec<Vec<u8>>, rrsigs: Vec<Vec<u8>>, min_ttl: Duration) {
+        let ttl = min_ttl.min(DNS_MAX_POSITIVE_TTL);
+        self.insert(
+            name,
+            record_type,
+            CachedAnswer {
+                records,
+                rrsigs,
+                expires_at: Instant::now() + ttl,
+                negative: false,
+            },
+        );
+    }
+
+    pub fn insert_negative(&mut self, name: &str, record_type: u16) {
+        self.insert(
+            name,
+            record_type,
+            CachedAnswer {
+                records: Vec::new(),
+                rrsigs: Vec::new(),
+                expires_at: Instant::now() + DNS_NEGATIVE_TTL,
+                negative: true,
+            },
+        );
+    }
+
+    fn insert(&mut self, name: &str, record_type: u16, answer: CachedAnswer) {
+        let key = (name.to_ascii_lowercase(), record_type);
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key.clone(), answer);
+        self.touch(&key);
+    }
+
+    /// Drops all cached answers; hooked into [`Reloader::reload`] so a
+    /// config reload that changes the upstream server list doesn't leave
+    /// stale answers from the old servers behind.
+    pub fn flush(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
 pub fn to_internal(conf: Config) -> Result<internal::Config> {
+    for diagnostic in validate(&conf) {
+        match diagnostic.severity {
+            Severity::Error => return Err(anyhow!("{}", diagnostic.message)),
+            Severity::Warn => log::warn!("{}", diagnostic.message),
+        }
+    }
+
     let mut log = internal::Log::new();
     if let Some(ext_general) = &conf.general {
         if let Some(ext_loglevel) = &ext_general.loglevel {
@@ -602,6 +1186,8 @@ pub fn to_internal(conf: Config) -> Result<internal::Config> {
                 }
                 if let Some(ext_mtu) = ext_tun.mtu {
                     settings.mtu = ext_mtu;
+                } else if ext_tun.mtu_auto {
+                    settings.mtu = resolve_auto_mtu(ext_tun.gateway.as_ref());
                 } else {
                     settings.mtu = 1500;
                 }
@@ -630,6 +1216,16 @@ pub fn to_internal(conf: Config) -> Result<internal::Config> {
                     outbounds.push(outbound);
                 }
                 "shadowsocks" => {
+                    // SIP003 plugin obfs modes are translated onto the same
+                    // tls/ws chain actors the vmess/vless arms use
+                    let (plugin_tls, plugin_ws, plugin_host, plugin_path) = match &ext_proxy.plugin {
+                        Some(plugin) => translate_plugin(plugin, ext_proxy.plugin_opts.as_deref().unwrap_or("")),
+                        None => (false, false, None, None),
+                    };
+                    let use_tls = ext_proxy.tls.unwrap_or(false) || plugin_tls;
+                    let use_ws = ext_proxy.ws.unwrap_or(false) || plugin_ws;
+
+                    // shadowsocks
                     let mut settings = internal::ShadowsocksOutboundSettings::new();
                     if let Some(ext_address) = &ext_proxy.address {
                         settings.address = ext_address.clone();
@@ -647,6 +1243,73 @@ pub fn to_internal(conf: Config) -> Result<internal::Config> {
                     }
                     let settings = settings.write_to_bytes().unwrap();
                     outbound.settings = settings;
+
+                    // plain shadowsocks, no tls/ws/plugin stacked in front:
+                    // same shape as the "direct"/"drop" arms, no chain
+                    if !use_tls && !use_ws {
+                        outbounds.push(outbound);
+                        continue;
+                    }
+                    outbound.tag = format!("{}_shadowsocks_xxx", ext_proxy.tag.clone());
+
+                    // tls
+                    let mut tls_outbound = internal::Outbound::new();
+                    tls_outbound.protocol = "tls".to_string();
+                    tls_outbound.bind = ext_proxy.interface.clone();
+                    let mut tls_settings = internal::TlsOutboundSettings::new();
+                    if let Some(ext_sni) = &ext_proxy.sni {
+                        tls_settings.server_name = ext_sni.clone();
+                    } else if let Some(plugin_host) = &plugin_host {
+                        tls_settings.server_name = plugin_host.clone();
+                    }
+                    let tls_settings = tls_settings.write_to_bytes().unwrap();
+                    tls_outbound.settings = tls_settings;
+                    tls_outbound.tag = format!("{}_tls_xxx", ext_proxy.tag.clone());
+
+                    // ws
+                    let mut ws_outbound = internal::Outbound::new();
+                    ws_outbound.protocol = "ws".to_string();
+                    ws_outbound.bind = ext_proxy.interface.clone();
+                    let mut ws_settings = internal::WebSocketOutboundSettings::new();
+                    if let Some(ext_ws_path) = &ext_proxy.ws_path {
+                        ws_settings.path = ext_ws_path.clone();
+                    } else if let Some(plugin_path) = &plugin_path {
+                        ws_settings.path = plugin_path.clone();
+                    } else {
+                        ws_settings.path = "/".to_string();
+                    }
+                    let ws_headers = build_ws_headers(ext_proxy);
+                    if !ws_headers.is_empty() {
+                        ws_settings.headers = ws_headers;
+                    }
+                    let ws_settings = ws_settings.write_to_bytes().unwrap();
+                    ws_outbound.settings = ws_settings;
+                    ws_outbound.tag = format!("{}_ws_xxx", ext_proxy.tag.clone());
+
+                    // chain
+                    let mut chain_outbound = internal::Outbound::new();
+                    chain_outbound.tag = ext_proxy.tag.clone();
+                    let mut chain_settings = internal::ChainOutboundSettings::new();
+                    if use_tls {
+                        chain_settings.actors.push(tls_outbound.tag.clone());
+                    }
+                    if use_ws {
+                        chain_settings.actors.push(ws_outbound.tag.clone());
+                    }
+                    chain_settings.actors.push(outbound.tag.clone());
+                    let chain_settings = chain_settings.write_to_bytes().unwrap();
+                    chain_outbound.settings = chain_settings;
+                    chain_outbound.protocol = "chain".to_string();
+
+                    // always push chain first, in case there isn't final rule,
+                    // the chain outbound will be the default one to use
+                    outbounds.push(chain_outbound);
+                    if use_tls {
+                        outbounds.push(tls_outbound);
+                    }
+                    if use_ws {
+                        outbounds.push(ws_outbound);
+                    }
                     outbounds.push(outbound);
                 }
                 "trojan" => {
@@ -716,6 +1379,10 @@ pub fn to_internal(conf: Config) -> Result<internal::Config> {
                     } else {
                         ws_settings.path = "/".to_string();
                     }
+                    let ws_headers = build_ws_headers(ext_proxy);
+                    if !ws_headers.is_empty() {
+                        ws_settings.headers = ws_headers;
+                    }
                     let ws_settings = ws_settings.write_to_bytes().unwrap();
                     ws_outbound.settings = ws_settings;
                     ws_outbound.tag = format!("{}_ws_xxx", ext_proxy.tag.clone());
@@ -795,6 +1462,10 @@ pub fn to_internal(conf: Config) -> Result<internal::Config> {
                     } else {
                         ws_settings.path = "/".to_string();
                     }
+                    let ws_headers = build_ws_headers(ext_proxy);
+                    if !ws_headers.is_empty() {
+                        ws_settings.headers = ws_headers;
+                    }
                     let ws_settings = ws_settings.write_to_bytes().unwrap();
                     ws_outbound.settings = ws_settings;
                     ws_outbound.tag = format!("{}_ws_xxx", ext_proxy.tag.clone());
@@ -972,6 +1643,41 @@ pub fn to_internal(conf: Config) -> Result<internal::Config> {
                     domain.value = ext_filter;
                     rule.domains.push(domain);
                 }
+                "DOMAIN-WILDCARD" => {
+                    // `internal::RoutingRule_Domain_Type` only has
+                    // FULL/DOMAIN/PLAIN; the router has no glob matcher to
+                    // act on a fourth variant even if one existed. The
+                    // overwhelmingly common wildcard shape, `*.example.com`,
+                    // is exactly a DOMAIN-SUFFIX match, so it's translated
+                    // onto the existing DOMAIN type and gets real matching,
+                    // same as a rule author who'd written DOMAIN-SUFFIX
+                    // directly; a bare literal degrades to FULL the same
+                    // way. A genuine glob (metacharacters anywhere other
+                    // than a leading `*.`) can't be represented, so reject
+                    // it now with the offending rule line instead of
+                    // silently accepting a rule that can never match.
+                    match WildcardDomain::compile(&ext_filter)? {
+                        WildcardDomain::Literal(value) => {
+                            let mut domain = internal::RoutingRule_Domain::new();
+                            domain.field_type = internal::RoutingRule_Domain_Type::FULL;
+                            domain.value = value;
+                            rule.domains.push(domain);
+                        }
+                        WildcardDomain::Suffix(value) => {
+                            let mut domain = internal::RoutingRule_Domain::new();
+                            domain.field_type = internal::RoutingRule_Domain_Type::DOMAIN;
+                            domain.value = value;
+                            rule.domains.push(domain);
+                        }
+                        WildcardDomain::Glob(_) => {
+                            return Err(anyhow!(
+                                "invalid rule \"DOMAIN-WILDCARD,{},{}\": only a leading \"*.\" or a literal domain are supported by the router, not a general glob",
+                                ext_filter,
+                                rule.target_tag
+                            ));
+                        }
+                    }
+                }
                 "GEOIP" => {
                     let mut mmdb = internal::RoutingRule_Mmdb::new();
                     let mut file = std::env::current_exe().unwrap();
@@ -1010,15 +1716,18 @@ pub fn to_internal(conf: Config) -> Result<internal::Config> {
         }
         if let Some(ext_dns_servers) = &ext_general.dns_server {
             for ext_dns_server in ext_dns_servers {
-                servers.push(ext_dns_server.clone());
+                servers.push(normalize_dns_server(ext_dns_server));
             }
             if servers.len() == 0 {
-                servers.push("114.114.114.114".to_string());
-                servers.push("8.8.8.8".to_string());
+                servers.push(normalize_dns_server("114.114.114.114"));
+                servers.push(normalize_dns_server("8.8.8.8"));
             }
             dns.servers = servers;
         }
     }
+    // `internal::DNS` has no `cache_size` field; the cache that bounds
+    // lookups lives in [`Reloader`] instead, sized from `[General]`'s
+    // `dns-cache-size`, or `DEFAULT_DNS_CACHE_SIZE` when that is absent.
 
     let mut config = internal::Config::new();
     config.log = protobuf::SingularPtrField::some(log);
@@ -1040,4 +1749,340 @@ where
     let lines = lines.collect();
     let config = from_lines(lines)?;
     to_internal(config)
+}
+
+// stable hash of a section entry's contents, used by `Reloader` to decide
+// whether a `[Proxy]`/`[Proxy Group]` entry actually changed across a
+// reload, rather than blindly recreating everything on every edit; `[Rule]`
+// entries are not hashed
+fn hash_entry<T: std::fmt::Debug>(v: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    format!("{:?}", v).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn tagged_hashes<T: std::fmt::Debug>(tag_of: impl Fn(&T) -> &str, items: &[T]) -> HashMap<String, u64> {
+    items
+        .iter()
+        .map(|item| (tag_of(item).to_string(), hash_entry(item)))
+        .collect()
+}
+
+// finds the previously built `tun`/`tun-fd` inbound, if any, so a reload can
+// keep using its already-open fd instead of trying to rebuild it
+fn find_tun_inbound(inbounds: &protobuf::RepeatedField<internal::Inbound>) -> Option<usize> {
+    inbounds.iter().position(|ib| ib.protocol == "tun")
+}
+
+/// Watches a `.conf` file and reloads routing on edit without tearing down
+/// the TUN inbound, whose fd cannot be re-opened once bound.
+///
+/// A reload re-parses the file into a [`Config`], rebuilds an
+/// [`internal::Config`] from it, and swaps the running config behind an
+/// `RwLock`. Proxies and proxy groups whose entry is unchanged (by hash)
+/// keep their old outbound; rules are not hashed, the routing rules are
+/// always taken from the new build as a whole. The `tun`/`tun-fd` inbound is always
+/// carried over from the running config, with only its `always-real-ip`
+/// fake-DNS excludes updated in place. A new file that fails to parse is
+/// logged and ignored, leaving the old config (and live connections) alone.
+///
+/// This only owns the config; it has no handle to a running router to push
+/// a reload into. Whatever builds the router from the initial
+/// [`current`](Self::current) should call [`subscribe`](Self::subscribe)
+/// too, and rebuild from each delivered [`internal::Config`] the same way.
+pub struct Reloader {
+    path: PathBuf,
+    ext: RwLock<Config>,
+    internal: RwLock<internal::Config>,
+    // owned outright (rather than registered separately by a caller that may
+    // not exist) so the cache always gets flushed on reload; resolvers that
+    // want to share it go through [`dns_cache`](Self::dns_cache)
+    dns_cache: Arc<Mutex<DnsCache>>,
+    // routers that want to react to a reload instead of polling `current()`
+    // subscribe here; a dead receiver (the router shut down) is just dropped
+    // from the list the next time a reload tries to notify it
+    subscribers: Mutex<Vec<std::sync::mpsc::Sender<internal::Config>>>,
+}
+
+impl Reloader {
+    pub fn new<P: AsRef<Path>>(path: P, internal: internal::Config) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let lines = read_lines(&path)?.collect();
+        let ext = from_lines(lines)?;
+        // `internal::DNS` has no `cache_size` field to carry this through
+        // `to_internal`, so the knob lives on `[General]` directly and
+        // sizes the cache `Reloader` already owns
+        let cache_size = ext
+            .general
+            .as_ref()
+            .and_then(|g| g.dns_cache_size)
+            .unwrap_or(DEFAULT_DNS_CACHE_SIZE);
+        Ok(Reloader {
+            path,
+            ext: RwLock::new(ext),
+            internal: RwLock::new(internal),
+            dns_cache: Arc::new(Mutex::new(DnsCache::new(cache_size as usize))),
+            subscribers: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn current(&self) -> internal::Config {
+        self.internal.read().unwrap().clone()
+    }
+
+    /// Registers interest in future reloads: whatever runtime owns the live
+    /// router/inbounds/outbounds should hold onto the returned `Receiver`
+    /// and, on each message, rebuild from the delivered [`internal::Config`]
+    /// the same way it did from [`current`](Self::current) at startup —
+    /// this module only ever swaps its own copy of the config, it has no
+    /// handle to a running router to push the change into itself.
+    pub fn subscribe(&self) -> std::sync::mpsc::Receiver<internal::Config> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Returns the running DNS answer cache, shared with whatever resolver
+    /// is wired up to serve lookups, so it can be consulted and populated
+    /// outside of this module; it is flushed automatically whenever
+    /// [`reload`](Self::reload) swaps in a new config.
+    pub fn dns_cache(&self) -> Arc<Mutex<DnsCache>> {
+        self.dns_cache.clone()
+    }
+
+    /// Re-reads the config file and, if it still parses, diffs it against
+    /// the running config section-by-section and swaps in the result.
+    pub fn reload(&self) -> Result<()> {
+        let lines = match read_lines(&self.path) {
+            Ok(lines) => lines.collect(),
+            Err(e) => {
+                log::warn!("failed to read {:?} on reload, keeping old config: {}", &self.path, e);
+                return Ok(());
+            }
+        };
+        let new_ext = match from_lines(lines) {
+            Ok(c) => c,
+            Err(e) => {
+                log::warn!("failed to parse {:?} on reload, keeping old config: {}", &self.path, e);
+                return Ok(());
+            }
+        };
+        let systemd_notify = new_ext.general.as_ref().map(|g| g.systemd_notify).unwrap_or(false);
+        if systemd_notify {
+            sd_notify("RELOADING=1\n");
+        }
+
+        let mut new_internal = to_internal(new_ext.clone())?;
+
+        let old_ext = self.ext.read().unwrap();
+        let old_internal = self.internal.read().unwrap();
+
+        let old_proxy_hashes = tagged_hashes(|p: &Proxy| p.tag.as_str(), old_ext.proxy.as_deref().unwrap_or(&[]));
+        let old_group_hashes = tagged_hashes(
+            |g: &ProxyGroup| g.tag.as_str(),
+            old_ext.proxy_group.as_deref().unwrap_or(&[]),
+        );
+        let new_proxy_hashes = tagged_hashes(|p: &Proxy| p.tag.as_str(), new_ext.proxy.as_deref().unwrap_or(&[]));
+        let new_group_hashes = tagged_hashes(
+            |g: &ProxyGroup| g.tag.as_str(),
+            new_ext.proxy_group.as_deref().unwrap_or(&[]),
+        );
+
+        // the outbound tagged with a [Proxy]/[Proxy Group] entry's own tag
+        // gets swapped back for the old instance when that entry is
+        // byte-for-byte unchanged; the `<tag>_tls_xxx`/`<tag>_ws_xxx`/...
+        // chain members carry other tags and always come from the new build
+        let old_outbounds_by_tag: HashMap<&str, &internal::Outbound> =
+            old_internal.outbounds.iter().map(|ob| (ob.tag.as_str(), ob)).collect();
+        for outbound in new_internal.outbounds.iter_mut() {
+            let unchanged = match (old_proxy_hashes.get(&outbound.tag), new_proxy_hashes.get(&outbound.tag)) {
+                (Some(old_h), Some(new_h)) => old_h == new_h,
+                _ => match (old_group_hashes.get(&outbound.tag), new_group_hashes.get(&outbound.tag)) {
+                    (Some(old_h), Some(new_h)) => old_h == new_h,
+                    _ => false,
+                },
+            };
+            if unchanged {
+                if let Some(old_ob) = old_outbounds_by_tag.get(outbound.tag.as_str()) {
+                    *outbound = (*old_ob).clone();
+                }
+            }
+        }
+
+        // the tun/tun-fd inbound can never be recreated (its fd cannot be
+        // re-opened), so always keep the running one, only patching the
+        // always-real-ip / fake-dns excludes in place
+        if let Some(old_idx) = find_tun_inbound(&old_internal.inbounds) {
+            let mut tun_inbound = old_internal.inbounds[old_idx].clone();
+            let always_real_ip = new_ext
+                .general
+                .as_ref()
+                .and_then(|g| g.always_real_ip.clone())
+                .unwrap_or_default();
+            let mut settings = internal::TUNInboundSettings::parse_from_bytes(&tun_inbound.settings)?;
+            settings.fake_dns_exclude = protobuf::RepeatedField::from_vec(always_real_ip);
+            tun_inbound.settings = settings.write_to_bytes()?;
+            if let Some(new_idx) = find_tun_inbound(&new_internal.inbounds) {
+                new_internal.inbounds[new_idx] = tun_inbound;
+            } else {
+                new_internal.inbounds.push(tun_inbound);
+            }
+        }
+
+        drop(old_ext);
+        drop(old_internal);
+
+        *self.ext.write().unwrap() = new_ext;
+        *self.internal.write().unwrap() = new_internal.clone();
+
+        self.dns_cache.lock().unwrap().flush();
+
+        self.subscribers.lock().unwrap().retain(|tx| tx.send(new_internal.clone()).is_ok());
+
+        if systemd_notify {
+            sd_notify("READY=1\n");
+        }
+        Ok(())
+    }
+
+    /// Spawns a background thread that polls the config file's mtime and
+    /// calls [`reload`](Self::reload) whenever it changes, so editing the
+    /// `.conf` actually reloads routing instead of requiring something else
+    /// to call `reload()`. A poll (rather than inotify) keeps this
+    /// dependency-free and portable; `interval` trades reload latency for
+    /// wakeups.
+    pub fn watch(self: Arc<Self>, interval: Duration) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            let mut last_modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+            loop {
+                std::thread::sleep(interval);
+                let modified = match std::fs::metadata(&self.path).and_then(|m| m.modified()) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        log::warn!("failed to stat {:?} while watching for changes: {}", &self.path, e);
+                        continue;
+                    }
+                };
+                if last_modified == Some(modified) {
+                    continue;
+                }
+                last_modified = Some(modified);
+                if let Err(e) = self.reload() {
+                    log::warn!("failed to reload {:?}: {}", &self.path, e);
+                }
+            }
+        })
+    }
+
+    /// Builds a [`Reloader`] and spawns the [`watch`](Self::watch) thread
+    /// polling once a second, so hot reload is live the moment this
+    /// returns. This does *not* send the initial systemd `READY=1` — at
+    /// this point only the config has been parsed, no inbound has bound a
+    /// socket yet, and telling systemd the unit is ready before that would
+    /// let dependent units start talking to ports nothing is listening on
+    /// yet. The caller should bind its inbounds and then call
+    /// [`notify_ready`](Self::notify_ready), which also starts the
+    /// watchdog pinger. This file only owns config parsing, not the
+    /// router/runtime that starts up with it; whatever entry point builds
+    /// the initial `internal::Config` from a `.conf` path should call
+    /// `Reloader::spawn` in place of a bare `Reloader::new`, then hold onto
+    /// the returned `Arc` (and `current()`/`subscribe()`) for the lifetime
+    /// of the process.
+    pub fn spawn<P: AsRef<Path>>(
+        path: P,
+        internal: internal::Config,
+    ) -> Result<Arc<Self>> {
+        let interval = Duration::from_secs(1);
+        let reloader = Arc::new(Reloader::new(path, internal)?);
+        reloader.clone().watch(interval);
+        Ok(reloader)
+    }
+
+    /// Tells systemd the unit is ready and starts the watchdog pinger,
+    /// gated on `systemd-notify = true` in `[General]`. Call this once,
+    /// after every inbound this process owns has finished binding —
+    /// calling it any earlier would report readiness before the service
+    /// can actually accept connections.
+    pub fn notify_ready(&self) {
+        if let Some(general) = self.ext.read().unwrap().general.as_ref() {
+            systemd_notify_ready(general, &self.internal.read().unwrap());
+            systemd_spawn_watchdog(general);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn sd_notify(msg: &str) {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    let path = match std::env::var_os("NOTIFY_SOCKET") {
+        Some(p) => p,
+        None => return,
+    };
+    let socket = match UnixDatagram::unbound() {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let path = path.to_string_lossy();
+    // systemd's sd_notify(3) treats a leading '@' as the Linux abstract
+    // socket namespace (the common case for a `Type=notify` unit's
+    // $NOTIFY_SOCKET), which `UnixDatagram::send_to` can't address directly
+    if let Some(name) = path.strip_prefix('@') {
+        if let Ok(addr) = SocketAddr::from_abstract_name(name.as_bytes()) {
+            let _ = socket.send_to_addr(msg.as_bytes(), &addr);
+        }
+        return;
+    }
+    let _ = socket.send_to(msg.as_bytes(), path.as_ref());
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sd_notify(_msg: &str) {}
+
+/// Reports readiness to systemd once every inbound in `config` is assumed to
+/// be bound, gated by `systemd-notify = true` in `[General]`. Meant to be
+/// called by the runtime right after it finishes binding listeners, so a
+/// `Type=notify` unit only becomes "ready" once TUN/SOCKS/HTTP are actually
+/// up instead of forking-and-hoping.
+pub fn systemd_notify_ready(general: &General, config: &internal::Config) {
+    if !general.systemd_notify {
+        return;
+    }
+    sd_notify(&format!(
+        "STATUS=inbounds={} outbounds={}\n",
+        config.inbounds.len(),
+        config.outbounds.len()
+    ));
+    sd_notify("READY=1\n");
+}
+
+/// Pings the systemd watchdog; call this on the interval given by the
+/// unit's `WatchdogSec=`, if `systemd-notify` is enabled.
+pub fn systemd_notify_watchdog(general: &General) {
+    if general.systemd_notify {
+        sd_notify("WATCHDOG=1\n");
+    }
+}
+
+/// Reads `$WATCHDOG_USEC` (set by systemd on the unit's `WatchdogSec=`) and,
+/// if present and `systemd-notify` is enabled, spawns a thread that pings
+/// the watchdog at half that interval, as `sd_watchdog_enabled(3)` and
+/// systemd.service(5) recommend, so a hung process gets killed and
+/// restarted instead of looking alive forever. A no-op when the unit has no
+/// watchdog configured.
+pub fn systemd_spawn_watchdog(general: &General) {
+    if !general.systemd_notify {
+        return;
+    }
+    let usec: u64 = match std::env::var("WATCHDOG_USEC").ok().and_then(|v| v.parse().ok()) {
+        Some(usec) if usec > 0 => usec,
+        _ => return,
+    };
+    let interval = Duration::from_micros(usec) / 2;
+    let general = general.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        systemd_notify_watchdog(&general);
+    });
 }
\ No newline at end of file